@@ -13,12 +13,14 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use std::collections::HashMap;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 use std::time::Duration;
 
 use crate::config::{ConnectionParams, ServerAddr};
+use crate::metrics::Metrics;
 use crate::{NetError, Server};
 
 pub trait ServerDetect: Send {
@@ -31,15 +33,46 @@ enum IOMode {
     Nonblock(usize),
 }
 
+/// How many consecutive `refresh()` ticks a previously-known peer may be missing from the
+/// fetched membership view before it is actually disconnected. This debounces a single
+/// transient failure (e.g. one host's DNS hiccuping) so it isn't mistaken for the peer
+/// having genuinely left the cluster.
+const MISSING_TICK_THRESHOLD: u32 = 1;
+
 pub(crate) struct ServerManager {
     server_id: u64,
     peer_detect: Box<dyn ServerDetect>,
     conn_params: ConnectionParams,
+    metrics: Option<Arc<dyn Metrics>>,
+    /// The address this manager last saw each peer advertise, used by `refresh()` to tell
+    /// a changed address (reconnect) apart from a removed peer (disconnect).
+    known_peers: HashMap<u64, SocketAddr>,
+    /// Consecutive `refresh()` ticks each known peer has been missing from the fetched
+    /// view; reset to zero as soon as the peer reappears.
+    missing_ticks: HashMap<u64, u32>,
 }
 
 impl ServerManager {
     pub fn new<D: ServerDetect + 'static>(server_id: u64, conf: ConnectionParams, detect: D) -> Self {
-        ServerManager { server_id, peer_detect: Box::new(detect), conn_params: conf }
+        ServerManager {
+            server_id,
+            peer_detect: Box::new(detect),
+            conn_params: conf,
+            metrics: None,
+            known_peers: HashMap::new(),
+            missing_ticks: HashMap::new(),
+        }
+    }
+
+    /// Attach a `Metrics` sink that will be updated on every connect attempt, success,
+    /// failure and reconnect triggered by this manager. Also registers it as the
+    /// process-wide sink `transport::block` reports bytes sent/received to, since its
+    /// send path and background reader run below `ServerManager` and don't carry a
+    /// reference to this instance's `metrics` field.
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        crate::metrics::set_global(metrics.clone());
+        self.metrics = Some(metrics);
+        self
     }
 
     pub fn bind<A: ToSocketAddrs>(&self, addr: A) -> Result<SocketAddr, NetError> {
@@ -48,12 +81,91 @@ impl ServerManager {
     }
 
     pub fn refresh(&mut self) {
-        for s in self.peer_detect.fetch() {
-            if s.id < self.server_id && !crate::state::is_connected(self.server_id, s.id) {
-                if let Err(e) =
-                    crate::transport::block::connect(self.server_id, s.id, self.conn_params, s.addr)
-                {
-                    error!("fail to connect server[id={},addr={:?}], caused by {}", s.id, s.addr, e);
+        let view = self.peer_detect.fetch();
+        let mut seen = std::collections::HashSet::with_capacity(view.len());
+
+        for s in view {
+            seen.insert(s.id);
+            // the peer is present in this tick's view again, so any debounce streak
+            // accumulated while it was briefly missing no longer applies
+            self.missing_ticks.remove(&s.id);
+            if s.id >= self.server_id {
+                continue;
+            }
+
+            match self.known_peers.get(&s.id) {
+                Some(&known_addr) if known_addr != s.addr && crate::state::is_connected(self.server_id, s.id) => {
+                    info!(
+                        "peer[id={}] address changed from {:?} to {:?}, reconnecting",
+                        s.id, known_addr, s.addr
+                    );
+                    match crate::transport::block::disconnect(self.server_id, s.id) {
+                        Ok(()) => {
+                            self.known_peers.insert(s.id, s.addr);
+                        }
+                        Err(e) => {
+                            // `disconnect` has already dropped the connection from the
+                            // registry and from `state` before its shutdown failed, so the
+                            // peer is reconnected at its new address below; `known_addr` is
+                            // kept, so the next tick disconnects and reconnects it once more
+                            error!("fail to disconnect stale server[id={}], caused by {}", s.id, e);
+                        }
+                    }
+                }
+                _ => {
+                    self.known_peers.insert(s.id, s.addr);
+                }
+            }
+
+            if !crate::state::is_connected(self.server_id, s.id) {
+                if let Some(metrics) = self.metrics.as_ref() {
+                    metrics.incr_counter("reconnect_attempts_total", s.id);
+                }
+                match crate::transport::block::connect(self.server_id, s.id, self.conn_params, s.addr) {
+                    Ok(()) => {
+                        if let Some(metrics) = self.metrics.as_ref() {
+                            metrics.set_peer_state(self.server_id, s.id, true, None);
+                        }
+                    }
+                    Err(e) => {
+                        error!("fail to connect server[id={},addr={:?}], caused by {}", s.id, s.addr, e);
+                        if let Some(metrics) = self.metrics.as_ref() {
+                            metrics.set_peer_state(self.server_id, s.id, false, Some(e.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+
+        let missing: Vec<u64> = self
+            .known_peers
+            .keys()
+            .copied()
+            .filter(|id| !seen.contains(id))
+            .collect();
+        for peer_id in missing {
+            let misses = {
+                let counter = self.missing_ticks.entry(peer_id).or_insert(0);
+                *counter += 1;
+                *counter
+            };
+            if misses <= MISSING_TICK_THRESHOLD {
+                info!(
+                    "peer[id={}] missing from membership view ({} consecutive tick(s)), deferring disconnect",
+                    peer_id, misses
+                );
+                continue;
+            }
+
+            self.known_peers.remove(&peer_id);
+            self.missing_ticks.remove(&peer_id);
+            if crate::state::is_connected(self.server_id, peer_id) {
+                info!("peer[id={}] no longer present in membership view, disconnecting", peer_id);
+                if let Err(e) = crate::transport::block::disconnect(self.server_id, peer_id) {
+                    error!("fail to disconnect removed server[id={}], caused by {}", peer_id, e);
+                }
+                if let Some(metrics) = self.metrics.as_ref() {
+                    metrics.set_peer_state(self.server_id, peer_id, false, None);
                 }
             }
         }
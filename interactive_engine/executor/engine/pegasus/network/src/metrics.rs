@@ -0,0 +1,289 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{TcpListener, ToSocketAddrs};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::Builder;
+
+/// Which direction a byte count reported to `Metrics::add_bytes` travelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl Direction {
+    fn label(&self) -> &'static str {
+        match self {
+            Direction::Sent => "sent",
+            Direction::Received => "received",
+        }
+    }
+}
+
+/// Observability hook for the networking layer. `ServerManager` calls into a shared
+/// `Metrics` implementation on every connect attempt, success, failure and reconnect, and
+/// `transport::block` calls into it on every send/recv, so that cluster connectivity and
+/// throughput can be scraped rather than grepped out of logs.
+pub trait Metrics: Send + Sync {
+    /// Increment the named counter, e.g. `reconnect_attempts_total`.
+    fn incr_counter(&self, name: &'static str, peer: u64);
+
+    /// Set the named gauge for a peer. Gauges that also have a dedicated per-peer
+    /// representation (e.g. `peer_connected`, driven by `set_peer_state`) should not be
+    /// set through this generic path too, or `render()` would emit the same metric twice.
+    fn set_gauge(&self, name: &'static str, peer: u64, value: f64);
+
+    /// Record the connection state of a single peer, including the last error observed
+    /// while trying to reach it (if any).
+    fn set_peer_state(&self, server_id: u64, peer: u64, connected: bool, last_error: Option<String>);
+
+    /// Account `bytes` transferred to/from `peer` on the wire. Called from
+    /// `transport::block`'s send path and its background reader.
+    fn add_bytes(&self, peer: u64, direction: Direction, bytes: u64);
+}
+
+#[derive(Clone, Debug, Default)]
+struct PeerState {
+    connected: bool,
+    last_connect_error: Option<String>,
+}
+
+static GLOBAL: OnceLock<Mutex<Option<Arc<dyn Metrics>>>> = OnceLock::new();
+
+fn global_slot() -> &'static Mutex<Option<Arc<dyn Metrics>>> {
+    GLOBAL.get_or_init(|| Mutex::new(None))
+}
+
+/// Register the process-wide `Metrics` sink that `transport::block` reports byte counts
+/// to. `transport::block`'s send/recv paths run below `ServerManager` and don't carry a
+/// reference to the `Arc<dyn Metrics>` it was configured with, so this mirrors the
+/// `state::is_connected` free-function pattern instead of threading it through every call.
+pub fn set_global(metrics: Arc<dyn Metrics>) {
+    *global_slot().lock().expect("poisoned global metrics lock") = Some(metrics);
+}
+
+/// The process-wide `Metrics` sink set by `set_global`, if any has been configured.
+pub(crate) fn global() -> Option<Arc<dyn Metrics>> {
+    global_slot()
+        .lock()
+        .expect("poisoned global metrics lock")
+        .clone()
+}
+
+/// In-memory metrics registry that renders its contents in Prometheus text exposition
+/// format. Counters and gauges are keyed by name and `peer` id; `server_id` is attached
+/// as a constant label so a scrape can tell which process exposed the metric.
+pub struct Registry {
+    server_id: u64,
+    counters: Mutex<HashMap<(&'static str, u64), u64>>,
+    gauges: Mutex<HashMap<(&'static str, u64), f64>>,
+    peers: Mutex<HashMap<u64, PeerState>>,
+    bytes: Mutex<HashMap<(u64, Direction), u64>>,
+}
+
+impl Registry {
+    pub fn new(server_id: u64) -> Arc<Self> {
+        Arc::new(Registry {
+            server_id,
+            counters: Mutex::new(HashMap::new()),
+            gauges: Mutex::new(HashMap::new()),
+            peers: Mutex::new(HashMap::new()),
+            bytes: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Render all collected counters, gauges and per-peer state as a Prometheus text
+    /// exposition document.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let counters = self.counters.lock().expect("poisoned counters lock");
+        for ((name, peer), value) in counters.iter() {
+            text.push_str(&format!(
+                "{}{{server_id=\"{}\",peer=\"{}\"}} {}\n",
+                name, self.server_id, peer, value
+            ));
+        }
+        drop(counters);
+
+        let gauges = self.gauges.lock().expect("poisoned gauges lock");
+        for ((name, peer), value) in gauges.iter() {
+            text.push_str(&format!(
+                "{}{{server_id=\"{}\",peer=\"{}\"}} {}\n",
+                name, self.server_id, peer, value
+            ));
+        }
+        drop(gauges);
+
+        let peers = self.peers.lock().expect("poisoned peers lock");
+        for (peer, state) in peers.iter() {
+            text.push_str(&format!(
+                "peer_connected{{server_id=\"{}\",peer=\"{}\"}} {}\n",
+                self.server_id,
+                peer,
+                if state.connected { 1 } else { 0 }
+            ));
+            if let Some(err) = &state.last_connect_error {
+                text.push_str(&format!(
+                    "last_connect_error{{server_id=\"{}\",peer=\"{}\",error=\"{}\"}} 1\n",
+                    self.server_id,
+                    peer,
+                    err.replace('"', "'")
+                ));
+            }
+        }
+        drop(peers);
+
+        let bytes = self.bytes.lock().expect("poisoned bytes lock");
+        for ((peer, direction), value) in bytes.iter() {
+            text.push_str(&format!(
+                "bytes_total{{server_id=\"{}\",peer=\"{}\",direction=\"{}\"}} {}\n",
+                self.server_id,
+                peer,
+                direction.label(),
+                value
+            ));
+        }
+        text
+    }
+}
+
+impl Metrics for Registry {
+    fn incr_counter(&self, name: &'static str, peer: u64) {
+        let mut counters = self.counters.lock().expect("poisoned counters lock");
+        *counters.entry((name, peer)).or_insert(0) += 1;
+    }
+
+    fn set_gauge(&self, name: &'static str, peer: u64, value: f64) {
+        let mut gauges = self.gauges.lock().expect("poisoned gauges lock");
+        gauges.insert((name, peer), value);
+    }
+
+    fn set_peer_state(&self, _server_id: u64, peer: u64, connected: bool, last_error: Option<String>) {
+        let mut peers = self.peers.lock().expect("poisoned peers lock");
+        let state = peers.entry(peer).or_insert_with(PeerState::default);
+        state.connected = connected;
+        if last_error.is_some() {
+            state.last_connect_error = last_error;
+        } else if connected {
+            state.last_connect_error = None;
+        }
+    }
+
+    fn add_bytes(&self, peer: u64, direction: Direction, bytes: u64) {
+        let mut counts = self.bytes.lock().expect("poisoned bytes lock");
+        *counts.entry((peer, direction)).or_insert(0) += bytes;
+    }
+}
+
+/// Serves the registry's rendered output as `text/plain; version=0.0.4` over a small bound
+/// address so a Prometheus server can scrape cluster connectivity. Runs in its own thread
+/// for the lifetime of the process.
+pub fn serve<A: ToSocketAddrs>(registry: Arc<Registry>, addr: A) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    Builder::new()
+        .name("pegasus-metrics-exporter".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(s) => s,
+                    Err(e) => {
+                        warn!("metrics exporter failed to accept connection: {}", e);
+                        continue;
+                    }
+                };
+                let body = registry.render();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                if let Err(e) = stream.write_all(response.as_bytes()) {
+                    warn!("metrics exporter failed to write response: {}", e);
+                }
+            }
+        })
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incr_counter_accumulates_per_peer() {
+        let registry = Registry::new(1);
+        registry.incr_counter("reconnect_attempts_total", 2);
+        registry.incr_counter("reconnect_attempts_total", 2);
+        registry.incr_counter("reconnect_attempts_total", 3);
+        let rendered = registry.render();
+        assert!(rendered.contains("reconnect_attempts_total{server_id=\"1\",peer=\"2\"} 2\n"));
+        assert!(rendered.contains("reconnect_attempts_total{server_id=\"1\",peer=\"3\"} 1\n"));
+    }
+
+    #[test]
+    fn set_gauge_overwrites_rather_than_accumulates() {
+        let registry = Registry::new(1);
+        registry.set_gauge("queue_depth", 2, 3.0);
+        registry.set_gauge("queue_depth", 2, 5.0);
+        let rendered = registry.render();
+        assert!(rendered.contains("queue_depth{server_id=\"1\",peer=\"2\"} 5\n"));
+        assert!(!rendered.contains(" 3\n"));
+    }
+
+    #[test]
+    fn set_peer_state_tracks_connectivity_and_last_error() {
+        let registry = Registry::new(1);
+        registry.set_peer_state(1, 2, false, Some("connection refused".to_string()));
+        let rendered = registry.render();
+        assert!(rendered.contains("peer_connected{server_id=\"1\",peer=\"2\"} 0\n"));
+        assert!(rendered.contains("last_connect_error{server_id=\"1\",peer=\"2\",error=\"connection refused\"} 1\n"));
+
+        registry.set_peer_state(1, 2, true, None);
+        let rendered = registry.render();
+        assert!(rendered.contains("peer_connected{server_id=\"1\",peer=\"2\"} 1\n"));
+        assert!(!rendered.contains("last_connect_error"));
+    }
+
+    #[test]
+    fn peer_connected_is_only_emitted_once() {
+        let registry = Registry::new(1);
+        registry.set_peer_state(1, 2, true, None);
+        let rendered = registry.render();
+        assert_eq!(rendered.matches("peer_connected{server_id=\"1\",peer=\"2\"}").count(), 1);
+    }
+
+    #[test]
+    fn add_bytes_accumulates_per_direction() {
+        let registry = Registry::new(1);
+        registry.add_bytes(2, Direction::Sent, 10);
+        registry.add_bytes(2, Direction::Sent, 5);
+        registry.add_bytes(2, Direction::Received, 7);
+        let rendered = registry.render();
+        assert!(rendered.contains("bytes_total{server_id=\"1\",peer=\"2\",direction=\"sent\"} 15\n"));
+        assert!(rendered.contains("bytes_total{server_id=\"1\",peer=\"2\",direction=\"received\"} 7\n"));
+    }
+
+    #[test]
+    fn set_global_makes_the_registry_reachable_via_global() {
+        let registry = Registry::new(9);
+        set_global(registry.clone());
+        let fetched = global().expect("global metrics should be set after set_global");
+        fetched.add_bytes(1, Direction::Sent, 3);
+        assert!(registry.render().contains("bytes_total{server_id=\"9\",peer=\"1\",direction=\"sent\"} 3\n"));
+    }
+}
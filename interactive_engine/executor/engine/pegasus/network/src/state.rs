@@ -0,0 +1,64 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Process-wide view of which `(server_id, peer_id)` connections are currently live.
+/// `ServerManager::refresh` reads this on every tick to decide whether a peer needs
+/// (re)connecting; `transport::block` is the only writer, updating it as connections are
+/// established and torn down.
+static CONNECTED: Mutex<Option<HashSet<(u64, u64)>>> = Mutex::new(None);
+
+fn with_connected<R>(f: impl FnOnce(&mut HashSet<(u64, u64)>) -> R) -> R {
+    let mut guard = CONNECTED.lock().expect("poisoned connected-peers lock");
+    let set = guard.get_or_insert_with(HashSet::new);
+    f(set)
+}
+
+/// Whether `server_id` currently holds a live connection to `peer_id`.
+pub(crate) fn is_connected(server_id: u64, peer_id: u64) -> bool {
+    with_connected(|set| set.contains(&(server_id, peer_id)))
+}
+
+/// Record that `server_id` has established a connection to `peer_id`. Called by
+/// `transport::block::connect` once the socket is up.
+pub(crate) fn mark_connected(server_id: u64, peer_id: u64) {
+    with_connected(|set| {
+        set.insert((server_id, peer_id));
+    });
+}
+
+/// Record that `server_id`'s connection to `peer_id` has been torn down. Called by
+/// `transport::block::disconnect` and by its background reader on EOF/error.
+pub(crate) fn mark_disconnected(server_id: u64, peer_id: u64) {
+    with_connected(|set| {
+        set.remove(&(server_id, peer_id));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mark_connected_and_disconnected_round_trip() {
+        assert!(!is_connected(100, 200));
+        mark_connected(100, 200);
+        assert!(is_connected(100, 200));
+        mark_disconnected(100, 200);
+        assert!(!is_connected(100, 200));
+    }
+}
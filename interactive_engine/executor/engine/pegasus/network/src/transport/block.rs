@@ -0,0 +1,178 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Mutex, OnceLock};
+use std::thread::Builder;
+
+use crate::config::ConnectionParams;
+use crate::metrics::Direction;
+use crate::{state, NetError};
+
+/// One blocking `TcpStream` per live `(server_id, peer_id)` connection this process holds.
+fn connections() -> &'static Mutex<HashMap<(u64, u64), TcpStream>> {
+    static CONNECTIONS: OnceLock<Mutex<HashMap<(u64, u64), TcpStream>>> = OnceLock::new();
+    CONNECTIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Bind a listening socket for `server_id` and return the address it ended up bound to.
+pub(crate) fn listen_on<A: ToSocketAddrs>(
+    _server_id: u64, _conn_params: ConnectionParams, addr: A,
+) -> Result<SocketAddr, NetError> {
+    let listener = TcpListener::bind(addr)?;
+    Ok(listener.local_addr()?)
+}
+
+/// Open a blocking connection from `server_id` to `peer_id` at `addr`, register it so
+/// `state::is_connected` reports it and `send` can reach it, and start a background reader
+/// that reports received bytes to the configured `Metrics` sink until the peer disconnects.
+pub(crate) fn connect(
+    server_id: u64, peer_id: u64, _conn_params: ConnectionParams, addr: SocketAddr,
+) -> Result<(), NetError> {
+    let stream = TcpStream::connect(addr)?;
+    let reader = stream.try_clone()?;
+    connections()
+        .lock()
+        .expect("poisoned connections lock")
+        .insert((server_id, peer_id), stream);
+    state::mark_connected(server_id, peer_id);
+    spawn_reader(server_id, peer_id, reader);
+    Ok(())
+}
+
+/// Tear down the connection from `server_id` to `peer_id`, if one is open.
+pub(crate) fn disconnect(server_id: u64, peer_id: u64) -> Result<(), NetError> {
+    let stream = connections()
+        .lock()
+        .expect("poisoned connections lock")
+        .remove(&(server_id, peer_id));
+    state::mark_disconnected(server_id, peer_id);
+    if let Some(stream) = stream {
+        stream.shutdown(Shutdown::Both)?;
+    }
+    Ok(())
+}
+
+/// Write `data` to `server_id`'s connection to `peer_id` and report the bytes sent to the
+/// configured `Metrics` sink.
+#[allow(dead_code)]
+pub(crate) fn send(server_id: u64, peer_id: u64, data: &[u8]) -> Result<(), NetError> {
+    let mut conns = connections().lock().expect("poisoned connections lock");
+    let stream = conns.get_mut(&(server_id, peer_id)).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotConnected, format!("peer[id={}] not connected", peer_id))
+    })?;
+    stream.write_all(data)?;
+    if let Some(metrics) = crate::metrics::global() {
+        metrics.add_bytes(peer_id, Direction::Sent, data.len() as u64);
+    }
+    Ok(())
+}
+
+fn spawn_reader(server_id: u64, peer_id: u64, mut stream: TcpStream) {
+    let name = format!("pegasus-transport-reader-{}", peer_id);
+    Builder::new()
+        .name(name)
+        .spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match stream.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        if let Some(metrics) = crate::metrics::global() {
+                            metrics.add_bytes(peer_id, Direction::Received, n as u64);
+                        }
+                    }
+                    Err(e) => {
+                        warn!("transport reader for peer[id={}] failed, caused by {}", peer_id, e);
+                        break;
+                    }
+                }
+            }
+            if let Err(e) = disconnect(server_id, peer_id) {
+                error!("fail to clean up connection to peer[id={}] after reader exit, caused by {}", peer_id, e);
+            }
+        })
+        .expect("fail to spawn transport reader thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::metrics::Registry;
+
+    #[test]
+    fn send_errors_when_peer_not_connected() {
+        assert!(send(700, 701, b"x").is_err());
+    }
+
+    #[test]
+    fn disconnect_is_a_noop_when_nothing_is_connected() {
+        assert!(disconnect(700, 702).is_ok());
+        assert!(!state::is_connected(700, 702));
+    }
+
+    #[test]
+    fn send_and_background_reader_report_bytes_via_metrics() {
+        // Bypasses `connect` (which needs a real `ConnectionParams`) and wires up the
+        // connection registry directly, so this exercises the same `send`/reader code
+        // `connect`'s callers hit without depending on that type's construction.
+        let listener = TcpListener::bind("127.0.0.1:0").expect("fail to bind test listener");
+        let addr = listener.local_addr().expect("fail to read test listener addr");
+        let server_thread = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("fail to accept test connection");
+            let mut buf = [0u8; 16];
+            let n = stream.read(&mut buf).expect("fail to read from test connection");
+            stream
+                .write_all(&buf[..n])
+                .expect("fail to echo on test connection");
+        });
+
+        let client = TcpStream::connect(addr).expect("fail to connect to test listener");
+        let reader = client
+            .try_clone()
+            .expect("fail to clone test client stream");
+        connections()
+            .lock()
+            .expect("poisoned connections lock")
+            .insert((800, 801), client);
+        state::mark_connected(800, 801);
+
+        let registry = Registry::new(800);
+        crate::metrics::set_global(registry.clone());
+        spawn_reader(800, 801, reader);
+
+        send(800, 801, b"hello").expect("send should succeed once connected");
+        server_thread.join().expect("test server thread panicked");
+
+        let mut rendered = registry.render();
+        for _ in 0..100 {
+            if rendered.contains("direction=\"received\"") {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+            rendered = registry.render();
+        }
+
+        assert!(rendered.contains("bytes_total{server_id=\"800\",peer=\"801\",direction=\"sent\"} 5\n"));
+        assert!(rendered.contains("bytes_total{server_id=\"800\",peer=\"801\",direction=\"received\"} 5\n"));
+
+        disconnect(800, 801).expect("fail to clean up test connection");
+    }
+}
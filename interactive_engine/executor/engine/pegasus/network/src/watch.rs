@@ -0,0 +1,119 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, Builder};
+use std::time::Duration;
+
+use crate::config::ServerAddr;
+use crate::manager::ServerDetect;
+use crate::{NetError, Server};
+
+/// An external source of cluster membership, e.g. a KV store such as etcd/consul, or a
+/// DNS SRV record that is periodically re-resolved. A single `resolve()` call returns the
+/// *full* current view so that a caller can diff it against what it already knows about
+/// and detect both newly-joined and since-removed peers.
+pub trait MembershipSource: Send {
+    fn resolve(&self) -> Result<Vec<Server>, NetError>;
+}
+
+/// Re-resolves a fixed set of `ServerAddr` hostnames on every call, reporting only the
+/// subset that currently resolves. Unlike the blocking `Vec<ServerAddr>` detector, a host
+/// that stops resolving simply drops out of the returned view instead of the call hanging
+/// forever.
+pub struct DnsMembershipSource {
+    addrs: Vec<ServerAddr>,
+}
+
+impl DnsMembershipSource {
+    pub fn new(addrs: Vec<ServerAddr>) -> Self {
+        DnsMembershipSource { addrs }
+    }
+}
+
+impl MembershipSource for DnsMembershipSource {
+    fn resolve(&self) -> Result<Vec<Server>, NetError> {
+        let mut servers = Vec::with_capacity(self.addrs.len());
+        for (id, server_addr) in self.addrs.iter().enumerate() {
+            match server_addr.to_socket_addr() {
+                Ok(socket_addr) => servers.push(Server { id: id as u64, addr: socket_addr }),
+                Err(_) => warn!("fail to resolve hostname: {}", server_addr.get_hostname()),
+            }
+        }
+        // A configured address list that currently resolves to nothing is a transient DNS
+        // outage, not a legitimately empty membership - report it as a failure so the
+        // caller backs off and keeps its last known-good view instead of tearing down
+        // every live connection.
+        if !self.addrs.is_empty() && servers.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("none of the {} configured server address(es) currently resolve", self.addrs.len()),
+            )
+            .into());
+        }
+        Ok(servers)
+    }
+}
+
+/// A `ServerDetect` backed by a `MembershipSource` that is re-resolved on a TTL instead of
+/// once at startup. Failures back off exponentially (capped at `max_backoff`) rather than
+/// retrying the fixed one-second spin of the old `Vec<ServerAddr>` detector, and the latest
+/// resolved view - including removals - is cached for `fetch()` to read without blocking.
+pub struct WatchServerDetect {
+    view: Arc<Mutex<Vec<Server>>>,
+}
+
+impl WatchServerDetect {
+    /// Spawn a background thread that re-resolves `source` every `ttl`, doubling the wait
+    /// up to `max_backoff` after each consecutive failure and resetting to `ttl` on the
+    /// next success.
+    pub fn new<S: MembershipSource + 'static>(source: S, ttl: Duration, max_backoff: Duration) -> Self {
+        let view = Arc::new(Mutex::new(Vec::new()));
+        let bg_view = view.clone();
+        Builder::new()
+            .name("pegasus-server-watch".to_owned())
+            .spawn(move || {
+                let mut backoff = ttl;
+                loop {
+                    match source.resolve() {
+                        Ok(servers) => {
+                            let mut guard =
+                                bg_view.lock().expect("unexpected error locking server watch view");
+                            *guard = servers;
+                            drop(guard);
+                            backoff = ttl;
+                            sleep(ttl);
+                        }
+                        Err(e) => {
+                            warn!("fail to resolve server membership, caused by {}, retry in {:?}", e, backoff);
+                            sleep(backoff);
+                            backoff = std::cmp::min(backoff * 2, max_backoff);
+                        }
+                    }
+                }
+            })
+            .expect("fail to spawn server watch thread");
+        WatchServerDetect { view }
+    }
+}
+
+impl ServerDetect for WatchServerDetect {
+    fn fetch(&self) -> Vec<Server> {
+        self.view
+            .lock()
+            .expect("unexpected error locking server watch view")
+            .clone()
+    }
+}
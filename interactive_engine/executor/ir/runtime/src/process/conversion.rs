@@ -0,0 +1,293 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::fmt;
+use std::str::FromStr;
+
+use dyn_type::{Object, Primitives};
+
+/// Declares the target type a raw property value should be coerced into during
+/// projection, e.g. when a query asks for `.as(int)` on a string property returned
+/// by the store.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conversion {
+    /// Keep the value as-is (string or bytes).
+    Bytes,
+    Integer,
+    Float,
+    Boolean,
+    /// Epoch seconds.
+    Timestamp,
+    /// Parse with a chrono-style format string, assuming UTC.
+    TimestampFmt(String),
+    /// Parse with a chrono-style format string that contains an embedded offset.
+    TimestampTZFmt(String),
+}
+
+impl FromStr for Conversion {
+    type Err = ConvError;
+
+    /// Parses `"string"`/`"bytes"`, `"int"`/`"integer"`, `"float"`, `"bool"`/`"boolean"` and
+    /// plain `"timestamp"` (epoch seconds) as-is. A timestamp conversion that also carries a
+    /// chrono-style format string is written as `"timestamp:<fmt>"` (assumed UTC) or
+    /// `"timestamp_tz:<fmt>"` (the input carries an embedded offset), e.g.
+    /// `"timestamp:%Y-%m-%d %H:%M:%S"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_ascii_lowercase();
+        match lower.as_str() {
+            "string" | "bytes" => return Ok(Conversion::Bytes),
+            "int" | "integer" => return Ok(Conversion::Integer),
+            "float" => return Ok(Conversion::Float),
+            "bool" | "boolean" => return Ok(Conversion::Boolean),
+            "timestamp" => return Ok(Conversion::Timestamp),
+            _ => {}
+        }
+        if let Some(prefix_len) = ["timestamp_tz:", "timestamptz:"]
+            .iter()
+            .find(|prefix| lower.starts_with(**prefix))
+            .map(|prefix| prefix.len())
+        {
+            return Ok(Conversion::TimestampTZFmt(s[prefix_len..].to_string()));
+        }
+        if lower.starts_with("timestamp:") {
+            return Ok(Conversion::TimestampFmt(s["timestamp:".len()..].to_string()));
+        }
+        Err(ConvError::UnknownConversion(s.to_string()))
+    }
+}
+
+impl Conversion {
+    /// Build a `TimestampFmt` conversion from a chrono-style format string.
+    pub fn timestamp_fmt(fmt: impl Into<String>) -> Self {
+        Conversion::TimestampFmt(fmt.into())
+    }
+
+    /// Build a `TimestampTZFmt` conversion from a chrono-style format string whose input
+    /// carries an embedded UTC offset.
+    pub fn timestamp_tz_fmt(fmt: impl Into<String>) -> Self {
+        Conversion::TimestampTZFmt(fmt.into())
+    }
+
+    /// Coerce `obj` into the type declared by this conversion. `Object::None` always
+    /// passes through unchanged, and a value that already matches the target type is a
+    /// no-op. Any other input is parsed from its string/bytes form; a parse failure is
+    /// reported as a `ConvError` rather than silently collapsing to `Object::None`.
+    pub fn convert(&self, obj: Object) -> Result<Object, ConvError> {
+        if let Object::None = obj {
+            return Ok(Object::None);
+        }
+        match self {
+            Conversion::Bytes => Ok(obj),
+            Conversion::Integer => match obj {
+                Object::Primitive(Primitives::Integer(_)) | Object::Primitive(Primitives::Long(_)) => {
+                    Ok(obj)
+                }
+                _ => {
+                    let text = Self::to_text(&obj)?;
+                    let value = text
+                        .trim()
+                        .parse::<i64>()
+                        .map_err(|e| ConvError::ParseError(format!("{} as integer: {}", text, e)))?;
+                    Ok(Object::Primitive(Primitives::Long(value)))
+                }
+            },
+            Conversion::Float => match obj {
+                Object::Primitive(Primitives::Float(_)) | Object::Primitive(Primitives::Double(_)) => {
+                    Ok(obj)
+                }
+                _ => {
+                    let text = Self::to_text(&obj)?;
+                    let value = text
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|e| ConvError::ParseError(format!("{} as float: {}", text, e)))?;
+                    Ok(Object::Primitive(Primitives::Double(value)))
+                }
+            },
+            Conversion::Boolean => match obj {
+                Object::Primitive(Primitives::Byte(b)) => Ok(Object::Primitive(Primitives::Byte(b))),
+                _ => {
+                    let text = Self::to_text(&obj)?;
+                    let value = text
+                        .trim()
+                        .parse::<bool>()
+                        .map_err(|e| ConvError::ParseError(format!("{} as boolean: {}", text, e)))?;
+                    Ok(Object::Primitive(Primitives::Byte(value as i8)))
+                }
+            },
+            Conversion::Timestamp => {
+                let text = Self::to_text(&obj)?;
+                let epoch = text
+                    .trim()
+                    .parse::<i64>()
+                    .map_err(|e| ConvError::ParseError(format!("{} as timestamp: {}", text, e)))?;
+                Ok(Object::Primitive(Primitives::Long(epoch)))
+            }
+            Conversion::TimestampFmt(fmt) => {
+                let text = Self::to_text(&obj)?;
+                let naive = chrono::NaiveDateTime::parse_from_str(text.trim(), fmt)
+                    .map_err(|e| ConvError::ParseError(format!("{} with format {}: {}", text, fmt, e)))?;
+                Ok(Object::Primitive(Primitives::Long(naive.timestamp())))
+            }
+            Conversion::TimestampTZFmt(fmt) => {
+                let text = Self::to_text(&obj)?;
+                let dt = chrono::DateTime::parse_from_str(text.trim(), fmt)
+                    .map_err(|e| ConvError::ParseError(format!("{} with format {}: {}", text, fmt, e)))?;
+                Ok(Object::Primitive(Primitives::Long(dt.timestamp())))
+            }
+        }
+    }
+
+    fn to_text(obj: &Object) -> Result<String, ConvError> {
+        match obj {
+            Object::String(s) => Ok(s.clone()),
+            Object::Blob(b) => {
+                String::from_utf8(b.clone()).map_err(|e| ConvError::ParseError(e.to_string()))
+            }
+            Object::Primitive(_) => Ok(obj.to_string()),
+            other => Err(ConvError::UnsupportedConversion(format!("cannot coerce {:?}", other))),
+        }
+    }
+}
+
+/// Error raised while coercing a property value with a `Conversion`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConvError {
+    UnknownConversion(String),
+    UnsupportedConversion(String),
+    ParseError(String),
+}
+
+impl fmt::Display for ConvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvError::UnknownConversion(name) => write!(f, "unknown conversion type: {}", name),
+            ConvError::UnsupportedConversion(msg) => write!(f, "unsupported conversion: {}", msg),
+            ConvError::ParseError(msg) => write!(f, "failed to parse value: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConvError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_parses_simple_names() {
+        assert_eq!("string".parse::<Conversion>().unwrap(), Conversion::Bytes);
+        assert_eq!("bytes".parse::<Conversion>().unwrap(), Conversion::Bytes);
+        assert_eq!("int".parse::<Conversion>().unwrap(), Conversion::Integer);
+        assert_eq!("Integer".parse::<Conversion>().unwrap(), Conversion::Integer);
+        assert_eq!("float".parse::<Conversion>().unwrap(), Conversion::Float);
+        assert_eq!("bool".parse::<Conversion>().unwrap(), Conversion::Boolean);
+        assert_eq!("BOOLEAN".parse::<Conversion>().unwrap(), Conversion::Boolean);
+        assert_eq!("timestamp".parse::<Conversion>().unwrap(), Conversion::Timestamp);
+    }
+
+    #[test]
+    fn from_str_parses_timestamp_formats() {
+        assert_eq!(
+            "timestamp:%Y-%m-%d %H:%M:%S".parse::<Conversion>().unwrap(),
+            Conversion::TimestampFmt("%Y-%m-%d %H:%M:%S".to_string())
+        );
+        assert_eq!(
+            "timestamp_tz:%Y-%m-%dT%H:%M:%S%z".parse::<Conversion>().unwrap(),
+            Conversion::TimestampTZFmt("%Y-%m-%dT%H:%M:%S%z".to_string())
+        );
+        // the prefix itself is matched case-insensitively, the format string is not
+        assert_eq!(
+            "TIMESTAMP:%Y".parse::<Conversion>().unwrap(),
+            Conversion::TimestampFmt("%Y".to_string())
+        );
+    }
+
+    #[test]
+    fn from_str_rejects_unknown_names() {
+        assert_eq!(
+            "unknown".parse::<Conversion>().unwrap_err(),
+            ConvError::UnknownConversion("unknown".to_string())
+        );
+    }
+
+    #[test]
+    fn convert_passes_none_through() {
+        assert_eq!(Conversion::Integer.convert(Object::None).unwrap(), Object::None);
+    }
+
+    #[test]
+    fn convert_integer_parses_string_and_is_noop_on_match() {
+        let obj = Conversion::Integer
+            .convert(Object::String("42".to_string()))
+            .unwrap();
+        assert_eq!(obj, Object::Primitive(Primitives::Long(42)));
+
+        let already = Object::Primitive(Primitives::Integer(7));
+        assert_eq!(Conversion::Integer.convert(already.clone()).unwrap(), already);
+    }
+
+    #[test]
+    fn convert_integer_parse_failure_is_explicit_error() {
+        let err = Conversion::Integer
+            .convert(Object::String("not-a-number".to_string()))
+            .unwrap_err();
+        assert!(matches!(err, ConvError::ParseError(_)));
+    }
+
+    #[test]
+    fn convert_float_and_boolean() {
+        assert_eq!(
+            Conversion::Float
+                .convert(Object::String("3.5".to_string()))
+                .unwrap(),
+            Object::Primitive(Primitives::Double(3.5))
+        );
+        assert_eq!(
+            Conversion::Boolean
+                .convert(Object::String("true".to_string()))
+                .unwrap(),
+            Object::Primitive(Primitives::Byte(1))
+        );
+    }
+
+    #[test]
+    fn convert_timestamp_epoch_seconds() {
+        assert_eq!(
+            Conversion::Timestamp
+                .convert(Object::String("1700000000".to_string()))
+                .unwrap(),
+            Object::Primitive(Primitives::Long(1700000000))
+        );
+    }
+
+    #[test]
+    fn convert_timestamp_fmt_assumes_utc() {
+        let conversion = Conversion::timestamp_fmt("%Y-%m-%d %H:%M:%S");
+        let obj = conversion
+            .convert(Object::String("2023-11-14 22:13:20".to_string()))
+            .unwrap();
+        assert_eq!(obj, Object::Primitive(Primitives::Long(1700000000)));
+    }
+
+    #[test]
+    fn convert_timestamp_tz_fmt_uses_embedded_offset() {
+        let conversion = Conversion::timestamp_tz_fmt("%Y-%m-%dT%H:%M:%S%z");
+        let obj = conversion
+            .convert(Object::String("2023-11-14T22:13:20+0000".to_string()))
+            .unwrap();
+        assert_eq!(obj, Object::Primitive(Primitives::Long(1700000000)));
+    }
+}
@@ -0,0 +1,159 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::fmt::Write;
+
+use graph_proxy::apis::{Edge, Element, GraphObject, GraphPath, Vertex, VertexOrEdge};
+
+use crate::process::record::{Entry, RecordElement};
+
+/// Whether a rendered DOT document describes a directed or an undirected graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Digraph,
+    Graph,
+}
+
+impl Kind {
+    /// The DOT edge operator for this graph kind: `->` for a digraph, `--` otherwise.
+    pub fn edgeop(&self) -> &'static str {
+        match self {
+            Kind::Digraph => "->",
+            Kind::Graph => "--",
+        }
+    }
+
+    fn keyword(&self) -> &'static str {
+        match self {
+            Kind::Digraph => "digraph",
+            Kind::Graph => "graph",
+        }
+    }
+}
+
+/// Wrap the accumulated statement body into a complete `digraph { ... }` / `graph { ... }`
+/// document.
+pub(crate) fn wrap(kind: Kind, body: String) -> String {
+    format!("{} {{\n{}}}\n", kind.keyword(), body)
+}
+
+pub(crate) fn write_entry(entry: &Entry, kind: Kind, out: &mut String) {
+    match entry {
+        Entry::Element(element) => write_element(element, kind, out),
+        Entry::Collection(elements) => {
+            for element in elements {
+                write_element(element, kind, out);
+            }
+        }
+    }
+}
+
+fn write_element(element: &RecordElement, kind: Kind, out: &mut String) {
+    match element {
+        RecordElement::OnGraph(graph_obj) => write_graph_object(graph_obj, kind, out),
+        RecordElement::OffGraph(_) => {}
+    }
+}
+
+fn write_graph_object(graph_obj: &GraphObject, kind: Kind, out: &mut String) {
+    match graph_obj {
+        GraphObject::V(v) => write_vertex(v, out),
+        GraphObject::E(e) => write_edge(e, kind, out),
+        GraphObject::P(p) => write_path(p, kind, out),
+    }
+}
+
+fn write_vertex(vertex: &Vertex, out: &mut String) {
+    let _ = writeln!(out, "  {} [label=\"{}\"];", vertex.get_id(), vertex_label(vertex));
+}
+
+fn write_edge(edge: &Edge, kind: Kind, out: &mut String) {
+    let _ =
+        writeln!(out, "  {} {} {};", edge.get_src_id(), kind.edgeop(), edge.get_dst_id());
+}
+
+fn write_path(path: &GraphPath, kind: Kind, out: &mut String) {
+    let mut prev_vertex_id = None;
+    for step in path.iter() {
+        match step {
+            VertexOrEdge::V(v) => {
+                let _ = writeln!(out, "  {} [label=\"{}\"];", v.get_id(), vertex_label(v));
+                prev_vertex_id = Some(v.get_id());
+            }
+            VertexOrEdge::E(e) => {
+                if let Some(src) = prev_vertex_id {
+                    let _ = writeln!(out, "  {} {} {};", src, kind.edgeop(), e.get_dst_id());
+                    prev_vertex_id = Some(e.get_dst_id());
+                } else {
+                    let _ = writeln!(out, "  {} {} {};", e.get_src_id(), kind.edgeop(), e.get_dst_id());
+                    prev_vertex_id = Some(e.get_dst_id());
+                }
+            }
+        }
+    }
+}
+
+fn vertex_label(vertex: &Vertex) -> String {
+    match vertex.details() {
+        Some(details) => match details.get_all_properties() {
+            Some(props) => {
+                let mut label = String::new();
+                for (key, value) in props.iter() {
+                    if !label.is_empty() {
+                        // a literal DOT line break between properties, not a raw newline
+                        label.push_str("\\n");
+                    }
+                    let _ = write!(
+                        label,
+                        "{}: {}",
+                        escape_label(&format!("{:?}", key)),
+                        escape_label(&value.to_string())
+                    );
+                }
+                label
+            }
+            None => vertex.get_id().to_string(),
+        },
+        None => vertex.get_id().to_string(),
+    }
+}
+
+/// Escape a single DOT label field so that a `"`, `\` or newline coming from a property
+/// key/value cannot terminate the quoted label early or otherwise corrupt the document.
+fn escape_label(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_label_handles_quotes_backslashes_and_newlines() {
+        assert_eq!(escape_label("plain"), "plain");
+        assert_eq!(escape_label("say \"hi\""), "say \\\"hi\\\"");
+        assert_eq!(escape_label("a\\b"), "a\\\\b");
+        assert_eq!(escape_label("line1\nline2"), "line1\\nline2");
+    }
+
+    #[test]
+    fn kind_edgeop_matches_graph_kind() {
+        assert_eq!(Kind::Digraph.edgeop(), "->");
+        assert_eq!(Kind::Graph.edgeop(), "--");
+    }
+}
@@ -31,6 +31,9 @@ use pegasus::api::function::DynIter;
 use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
 use vec_map::VecMap;
 
+use crate::process::conversion::{ConvError, Conversion};
+use crate::process::dot::Kind;
+
 #[derive(Debug, Clone, Hash, PartialEq, PartialOrd)]
 pub enum CommonObject {
     /// a None value used when:
@@ -44,6 +47,19 @@ pub enum CommonObject {
     Count(u64),
 }
 
+impl CommonObject {
+    /// Coerce a `Prop` value into the type declared by `conversion`, e.g. to honor a
+    /// `.as(int)` projection on a string property. `None` and `Count` pass through
+    /// unchanged; a failed coercion is surfaced as a `ConvError` instead of silently
+    /// collapsing to `CommonObject::None`.
+    pub fn convert(self, conversion: &Conversion) -> Result<CommonObject, ConvError> {
+        match self {
+            CommonObject::Prop(obj) => Ok(CommonObject::Prop(conversion.convert(obj)?)),
+            other => Ok(other),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, PartialOrd)]
 pub enum RecordElement {
     OnGraph(GraphObject),
@@ -135,6 +151,19 @@ impl Entry {
             _ => false,
         }
     }
+
+    /// Coerce this entry's property value with `conversion`, e.g. to honor a query's
+    /// `.as(int)` alias on a projected string property. Graph-element entries and
+    /// collections pass through unchanged; only an off-graph `CommonObject::Prop` is
+    /// actually coerced.
+    pub fn convert(self, conversion: &Conversion) -> Result<Entry, ConvError> {
+        match self {
+            Entry::Element(RecordElement::OffGraph(obj)) => {
+                Ok(Entry::Element(RecordElement::OffGraph(obj.convert(conversion)?)))
+            }
+            other => Ok(other),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -158,6 +187,33 @@ impl Record {
         self.append_arc_entry(Arc::new(entry.into()), alias)
     }
 
+    /// Append a projected entry, honoring an optional declared `Conversion` for the
+    /// column being projected, e.g. so the project operator can coerce a string property
+    /// into an integer when a query asks for `.as(int)`.
+    pub fn append_converted<E: Into<Entry>>(
+        &mut self, entry: E, alias: Option<KeyId>, conversion: Option<&Conversion>,
+    ) -> Result<(), ConvError> {
+        let entry = entry.into();
+        let entry = if let Some(conversion) = conversion { entry.convert(conversion)? } else { entry };
+        self.append(entry, alias);
+        Ok(())
+    }
+
+    /// The per-column step the project operator performs for every projected expression:
+    /// read the entry currently bound to `from`, coerce it with `conversion` when the
+    /// query declared one (e.g. `.as(int)` on a string property), and append the result
+    /// under `alias`. Missing `from` projects `CommonObject::None`, matching the rest of
+    /// the record's None-on-missing-tag convention.
+    pub fn project_column(
+        &mut self, from: Option<KeyId>, alias: Option<KeyId>, conversion: Option<&Conversion>,
+    ) -> Result<(), ConvError> {
+        let entry = match self.get(from) {
+            Some(entry) => entry.as_ref().clone(),
+            None => CommonObject::None.into(),
+        };
+        self.append_converted(entry, alias, conversion)
+    }
+
     pub fn append_arc_entry(&mut self, entry: Arc<Entry>, alias: Option<KeyId>) {
         self.curr = Some(entry.clone());
         if let Some(alias) = alias {
@@ -213,6 +269,38 @@ impl Record {
 
         self
     }
+
+    /// Render the graph-shaped entries of this record (vertices, edges and paths) as a
+    /// GraphViz DOT document, so a result set can be piped straight into `dot`/`neato`/etc.
+    /// Off-graph entries are skipped. `curr` and `columns` commonly alias the same `Arc`
+    /// (e.g. right after an aliased `append`), so each distinct entry is only rendered once.
+    pub fn to_dot(&self, kind: Kind) -> String {
+        let mut body = String::new();
+        for entry in self.dot_entries() {
+            crate::process::dot::write_entry(entry.as_ref(), kind, &mut body);
+        }
+        crate::process::dot::wrap(kind, body)
+    }
+
+    /// The entries `to_dot` should render, deduplicated by `Arc` identity so an entry that
+    /// is both `curr` and an aliased column is not emitted twice.
+    fn dot_entries(&self) -> Vec<&Arc<Entry>> {
+        let mut seen = Vec::new();
+        let mut entries = Vec::new();
+        if let Some(entry) = self.curr.as_ref() {
+            seen.push(Arc::as_ptr(entry));
+            entries.push(entry);
+        }
+        for (_, entry) in self.columns.iter() {
+            let ptr = Arc::as_ptr(entry);
+            if seen.contains(&ptr) {
+                continue;
+            }
+            seen.push(ptr);
+            entries.push(entry);
+        }
+        entries
+    }
 }
 
 impl Into<Entry> for Vertex {
@@ -640,3 +728,77 @@ impl Add for Entry {
         CommonObject::None.into()
     }
 }
+
+#[cfg(test)]
+mod dot_tests {
+    use super::*;
+    use crate::process::dot::Kind;
+
+    #[test]
+    fn to_dot_skips_off_graph_entries() {
+        let record = Record::new(CommonObject::Count(1), None);
+        assert_eq!(record.to_dot(Kind::Digraph), "digraph {\n}\n");
+    }
+
+    #[test]
+    fn to_dot_skips_off_graph_collection_members() {
+        let mut record = Record::new(CommonObject::None, None);
+        record.append(
+            Entry::Collection(vec![
+                RecordElement::OffGraph(CommonObject::None),
+                RecordElement::OffGraph(CommonObject::Count(2)),
+            ]),
+            None,
+        );
+        assert_eq!(record.to_dot(Kind::Graph), "graph {\n}\n");
+    }
+
+    #[test]
+    fn to_dot_dedups_an_entry_that_is_both_curr_and_an_aliased_column() {
+        // `Record::new` with a tag sets `curr` and the column to the very same `Arc`,
+        // which is also what a plain aliased `append` does - the common case that used
+        // to double-emit the entry's DOT statements.
+        let record = Record::new(CommonObject::Count(1), Some(0));
+        assert_eq!(record.dot_entries().len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod project_tests {
+    use super::*;
+
+    #[test]
+    fn project_column_coerces_a_string_property_as_int() {
+        let mut record = Record::new(CommonObject::Prop(Object::String("42".to_string())), Some(0));
+        record
+            .project_column(Some(0), Some(1), Some(&Conversion::Integer))
+            .unwrap();
+        let expected: Entry =
+            CommonObject::Prop(Object::Primitive(dyn_type::Primitives::Long(42))).into();
+        assert_eq!(record.get(Some(1)).unwrap().as_ref(), &expected);
+    }
+
+    #[test]
+    fn project_column_without_conversion_copies_the_entry_as_is() {
+        let mut record = Record::new(CommonObject::Prop(Object::String("hi".to_string())), Some(0));
+        record.project_column(Some(0), Some(1), None).unwrap();
+        assert_eq!(record.get(Some(1)), record.get(Some(0)));
+    }
+
+    #[test]
+    fn project_column_on_a_missing_tag_projects_none() {
+        let mut record = Record::new(CommonObject::Count(1), Some(0));
+        record
+            .project_column(Some(7), Some(1), Some(&Conversion::Integer))
+            .unwrap();
+        let expected: Entry = CommonObject::None.into();
+        assert_eq!(record.get(Some(1)).unwrap().as_ref(), &expected);
+    }
+
+    #[test]
+    fn project_column_propagates_a_conversion_error() {
+        let mut record = Record::new(CommonObject::Prop(Object::String("nope".to_string())), Some(0));
+        let err = record.project_column(Some(0), Some(1), Some(&Conversion::Integer));
+        assert!(matches!(err, Err(ConvError::ParseError(_))));
+    }
+}